@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use merge::Merge;
+use serde::Deserialize;
+
+/// User-defined command aliases, configured via the `[aliases]` table in the
+/// config profile (e.g. `sn = "snapshots --group-by host"`) and expanded
+/// before clap ever sees the arguments.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+pub struct Aliases(HashMap<String, String>);
+
+impl Aliases {
+    /// Look up the expansion for `name`, if it is a defined alias.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl Merge for Aliases {
+    fn merge(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_returns_none_for_unknown_alias() {
+        let aliases = Aliases::default();
+        assert_eq!(aliases.get("sn"), None);
+    }
+
+    #[test]
+    fn get_returns_the_defined_expansion() {
+        let aliases: Aliases = serde_json::from_str(r#"{"sn":"snapshots --group-by host"}"#).unwrap();
+        assert_eq!(aliases.get("sn"), Some("snapshots --group-by host"));
+    }
+
+    #[test]
+    fn merge_keeps_existing_entries_and_adds_new_ones() {
+        let mut base: Aliases = serde_json::from_str(r#"{"sn":"snapshots"}"#).unwrap();
+        let override_file: Aliases = serde_json::from_str(r#"{"full-backup":"backup --force"}"#).unwrap();
+        base.merge(override_file);
+        assert_eq!(base.get("sn"), Some("snapshots"));
+        assert_eq!(base.get("full-backup"), Some("backup --force"));
+    }
+
+    #[test]
+    fn merge_lets_the_merged_in_config_override_a_shared_key() {
+        let mut base: Aliases = serde_json::from_str(r#"{"sn":"snapshots"}"#).unwrap();
+        let override_file: Aliases = serde_json::from_str(r#"{"sn":"snapshots --group-by host"}"#).unwrap();
+        base.merge(override_file);
+        assert_eq!(base.get("sn"), Some("snapshots --group-by host"));
+    }
+}
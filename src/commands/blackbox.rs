@@ -0,0 +1,88 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Information accumulated over the course of a single `rustic` invocation,
+/// used to fill in the blackbox record no matter whether the command
+/// ultimately succeeds or fails.
+#[derive(Default)]
+pub struct RunContext {
+    pub command: String,
+    pub repository_id: Option<String>,
+}
+
+impl RunContext {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    duration_ms: u128,
+    command: &'a str,
+    repository_id: Option<&'a str>,
+    outcome: &'a str,
+    version: &'static str,
+}
+
+/// Append one JSON line describing this invocation to the blackbox log at
+/// `path`, creating it if necessary. `start_time` is the wall-clock time the
+/// invocation began, not the time this function happens to run.
+pub fn append_record(
+    path: &Path,
+    ctx: &RunContext,
+    start_time: SystemTime,
+    elapsed: Duration,
+    result: &Result<()>,
+) -> Result<()> {
+    let outcome = match result {
+        Ok(()) => "success".to_string(),
+        Err(err) => format!("error: {err}"),
+    };
+
+    let record = Record {
+        timestamp: humantime::format_rfc3339_seconds(start_time).to_string(),
+        duration_ms: elapsed.as_millis(),
+        command: &ctx.command,
+        repository_id: ctx.repository_id.as_deref(),
+        outcome: &outcome,
+        version: option_env!("PROJECT_VERSION").unwrap_or(env!("CARGO_PKG_VERSION")),
+    };
+
+    let line = serde_json::to_string(&record)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn record_uses_the_passed_in_start_time_not_now() {
+        let dir = std::env::temp_dir().join(format!("rustic-blackbox-test-{:?}", std::thread::current().id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blackbox.log");
+
+        let ctx = RunContext::new("rustic snapshots".to_string());
+        let start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        append_record(&path, &ctx, start_time, Duration::from_millis(42), &Ok(())).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&humantime::format_rfc3339_seconds(start_time).to_string()));
+        assert!(!contents.contains(&humantime::format_rfc3339_seconds(SystemTime::now()).to_string()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
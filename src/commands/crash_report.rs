@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+/// Write a self-contained crash-report file describing a failed invocation
+/// to `dir`, returning the path it was written to.
+pub fn write(
+    dir: &Path,
+    command_line: &str,
+    profile: &str,
+    repository_id: Option<&str>,
+    err: &anyhow::Error,
+) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let path = dir.join(format!("rustic-crash-{}.txt", timestamp.replace(':', "-")));
+    let version = option_env!("PROJECT_VERSION").unwrap_or(env!("CARGO_PKG_VERSION"));
+
+    let report = format!(
+        "rustic {version} crash report\n\
+         time: {timestamp}\n\
+         os/arch: {}/{}\n\
+         config profile: {profile}\n\
+         repository: {}\n\
+         command: {command_line}\n\
+         \n\
+         {err:?}\n",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        repository_id.unwrap_or("<not opened>"),
+    );
+
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_includes_command_profile_repository_and_error_chain() {
+        let dir = std::env::temp_dir().join(format!(
+            "rustic-crash-report-test-{:?}",
+            std::thread::current().id()
+        ));
+
+        let err = anyhow::anyhow!("inner cause").context("outer failure");
+        let path = write(&dir, "rustic backup /data", "work", Some("abc123"), &err).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+
+        assert!(contents.contains("rustic backup /data"));
+        assert!(contents.contains("config profile: work"));
+        assert!(contents.contains("repository: abc123"));
+        assert!(contents.contains("outer failure"));
+        assert!(contents.contains("inner cause"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn report_notes_when_no_repository_was_opened() {
+        let dir = std::env::temp_dir().join(format!(
+            "rustic-crash-report-test-norepo-{:?}",
+            std::thread::current().id()
+        ));
+
+        let err = anyhow::anyhow!("init failed");
+        let path = write(&dir, "rustic init", "rustic", None, &err).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+
+        assert!(contents.contains("repository: <not opened>"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
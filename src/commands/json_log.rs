@@ -0,0 +1,98 @@
+//! JSON log backend for `--log-format=json`.
+//!
+//! PARTIAL IMPLEMENTATION: the backlog item behind this flag asked for a
+//! full machine-readable mode — typed events for command results too
+//! (`{"type":"snapshot",...}`, `{"type":"summary",...}`), not just log
+//! lines, so scripts/GUIs could drive rustic entirely off stdout. Only the
+//! `log` stream (records of every level that passes `--log-level`) is
+//! covered here; every command's
+//! real output (snapshot tables, summaries — the part a script actually
+//! wants) still goes to stdout as plain human-readable text, untouched.
+//! Delivering the rest needs each command's `execute()` threaded with a
+//! shared output sink, which is follow-up work and not part of this file;
+//! this item should not be treated as fully closed until that lands.
+
+use log::{Level, Log, Metadata, Record, SetLoggerError};
+use serde::Serialize;
+
+/// A `log` backend that emits every log record as a single
+/// newline-delimited JSON object on stderr instead of the usual
+/// human-readable output. See the module docs for what this does and
+/// doesn't cover.
+struct JsonLogger {
+    level: log::LevelFilter,
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    level: &'a str,
+    message: String,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let event = Event {
+            kind: "log",
+            level: level_name(record.level()),
+            message: record.args().to_string(),
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            eprintln!("{line}");
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Install the JSON logger as the global `log` backend.
+pub fn init(level: log::LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_max_level(level);
+    log::set_boxed_logger(Box::new(JsonLogger { level }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_name_covers_all_levels() {
+        assert_eq!(level_name(Level::Error), "error");
+        assert_eq!(level_name(Level::Warn), "warn");
+        assert_eq!(level_name(Level::Info), "info");
+        assert_eq!(level_name(Level::Debug), "debug");
+        assert_eq!(level_name(Level::Trace), "trace");
+    }
+
+    #[test]
+    fn event_serializes_as_typed_log_record() {
+        let event = Event {
+            kind: "log",
+            level: level_name(Level::Warn),
+            message: "disk is getting full".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"log","level":"warn","message":"disk is getting full"}"#
+        );
+    }
+}
@@ -13,17 +13,21 @@ use crate::repository::{Repository, RepositoryOptions};
 
 use helpers::*;
 
+mod aliases;
 mod backup;
+mod blackbox;
 mod cat;
 mod check;
 mod completions;
 mod config;
 mod copy;
+mod crash_report;
 mod diff;
 mod dump;
 mod forget;
 mod helpers;
 mod init;
+mod json_log;
 mod key;
 mod list;
 mod ls;
@@ -31,12 +35,14 @@ mod merge_cmd;
 mod prune;
 mod repair;
 mod repoinfo;
+mod requirements;
 mod restore;
 mod rustic_config;
 mod self_update;
 mod snapshots;
 mod tag;
 
+use aliases::Aliases;
 use rustic_config::RusticConfig;
 
 #[derive(Parser)]
@@ -97,6 +103,38 @@ struct GlobalOpts {
     )]
     #[serde_as(as = "Option<DisplayFromStr>")]
     progress_interval: Option<humantime::Duration>,
+
+    /// Append a structured audit-log record of this invocation to FILE
+    #[clap(long, global = true, env = "RUSTIC_BLACKBOX", value_name = "FILE")]
+    blackbox: Option<PathBuf>,
+
+    /// Emit log messages as newline-delimited JSON on stderr instead of human-readable text.
+    /// Note: this is a partial implementation of machine-readable output — command
+    /// result output (tables, summaries) doesn't go through it yet; see `json_log`.
+    #[clap(long, global = true, env = "RUSTIC_LOG_FORMAT", value_enum)]
+    log_format: Option<LogFormat>,
+
+    /// Directory to write a crash-report file to if the command fails [default: the system temp dir]
+    #[clap(long, global = true, env = "RUSTIC_CRASH_DIR", value_name = "DIR")]
+    crash_dir: Option<PathBuf>,
+
+    /// Proceed even if the repository declares a config version this build doesn't support.
+    /// Only affects commands that would otherwise refuse to modify the repository.
+    #[clap(long, global = true, env = "RUSTIC_IGNORE_UNSUPPORTED")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    ignore_unsupported: bool,
+}
+
+/// Output format for the `log` stream (not yet command result output — see
+/// [`json_log`] for the current scope of `--log-format=json`).
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum LogFormat {
+    /// Human-readable output, suitable for a terminal
+    #[default]
+    Text,
+    /// Newline-delimited JSON log events on stderr
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -166,8 +204,177 @@ enum Command {
     Tag(tag::Opts),
 }
 
+/// Maximum number of alias expansions to follow before giving up, so that
+/// `foo = "foo"` (or a longer cycle) can't send us into an infinite loop.
+const MAX_ALIAS_EXPANSIONS: usize = 10;
+
+/// Find the `-P`/`--config-profile` value in the raw args, without going
+/// through clap. We need the profile before we can load the config file that
+/// tells us how to expand aliases, which in turn has to happen before clap
+/// parses the (possibly aliased) command line.
+fn config_profile_from_args(args: &[std::ffi::OsString]) -> String {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if let Some(value) = arg.strip_prefix("--config-profile=") {
+            return value.to_string();
+        }
+        if let Some(value) = arg.strip_prefix("-P") {
+            if !value.is_empty() {
+                return value.to_string();
+            }
+        }
+        if arg == "--config-profile" || arg == "-P" {
+            if let Some(value) = iter.next() {
+                return value.to_string_lossy().to_string();
+            }
+        }
+    }
+    "rustic".to_string()
+}
+
+/// The long/short names of every global flag that consumes a following
+/// value (e.g. `--config-profile`/`-P`), derived straight from clap's own
+/// `Command` so this stays correct as global options are added or removed.
+fn value_taking_flags(command: &clap::Command) -> std::collections::HashSet<String> {
+    // clap only fills in each argument's value count when the command is built.
+    let mut command = command.clone();
+    command.build();
+    let mut flags = std::collections::HashSet::new();
+    for arg in command.get_arguments() {
+        let takes_value = arg
+            .get_num_args()
+            .is_some_and(|range| range.max_values() > 0);
+        if !takes_value {
+            continue;
+        }
+        if let Some(long) = arg.get_long() {
+            flags.insert(format!("--{long}"));
+        }
+        if let Some(short) = arg.get_short() {
+            flags.insert(format!("-{short}"));
+        }
+    }
+    flags
+}
+
+/// How many argv tokens a short-flag cluster like `-n`, `-P`, `-Pwork`, or
+/// the bundle `-nP` consumes: just itself, or itself plus a following value
+/// token. Short flags can be bundled together (`-nP` is `-n` followed by
+/// `-P`), and only the *last* flag in a bundle may take a value — clap reads
+/// left to right and, on hitting a value-taking flag, treats any remaining
+/// characters as its glued-on value (`-nPwork`) or else consumes the next
+/// argv token (`-nP work`).
+fn short_flag_cluster_width(chars: &[char], value_flags: &std::collections::HashSet<String>) -> usize {
+    for (idx, ch) in chars.iter().enumerate() {
+        if value_flags.contains(&format!("-{ch}")) {
+            return if idx + 1 < chars.len() { 1 } else { 2 };
+        }
+    }
+    1
+}
+
+/// Find the index of the first "free" token in `args` — one that isn't a
+/// flag and isn't consumed as some flag's value — which is where the
+/// subcommand or alias lives. A naive "first token not starting with `-`"
+/// scan gets this wrong whenever a value-taking global option like
+/// `-P`/`--config-profile` appears before the subcommand, since it would
+/// match the option's *value* instead; it also has to account for bundled
+/// short flags like `-nP work`, where the value-taking flag isn't the first
+/// character of its token.
+fn first_free_token(args: &[std::ffi::OsString], value_flags: &std::collections::HashSet<String>) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let text = args[i].to_string_lossy();
+        if !text.starts_with('-') {
+            return Some(i);
+        }
+        if text == "--" {
+            return (i + 1 < args.len()).then_some(i + 1);
+        }
+        if text.starts_with("--") {
+            if text.contains('=') || !value_flags.contains(text.as_ref()) {
+                // `--flag=value` is one token; an unrecognized/boolean flag
+                // doesn't consume a following token either.
+                i += 1;
+            } else {
+                // `--flag value`: skip the value too.
+                i += 2;
+            }
+            continue;
+        }
+        if text.contains('=') {
+            i += 1;
+            continue;
+        }
+        let chars: Vec<char> = text.trim_start_matches('-').chars().collect();
+        i += short_flag_cluster_width(&chars, value_flags);
+    }
+    None
+}
+
+/// Expand a leading config-defined alias in `args`, mirroring how `cargo`
+/// resolves aliased subcommands. Built-in [`Command`] variants always win, so
+/// an alias can never shadow a real subcommand.
+fn expand_aliases(mut args: Vec<std::ffi::OsString>) -> Result<Vec<std::ffi::OsString>> {
+    use clap::CommandFactory;
+
+    let command_spec = Opts::command();
+    let value_flags = value_taking_flags(&command_spec);
+    let Some(pos) = first_free_token(&args, &value_flags) else {
+        return Ok(args);
+    };
+
+    let profile = config_profile_from_args(&args);
+    let Ok(config_file) = RusticConfig::new(&profile) else {
+        // No (or unreadable) config file: nothing to expand.
+        return Ok(args);
+    };
+    let mut aliases = Aliases::default();
+    config_file.merge_into("aliases", &mut aliases)?;
+
+    let known_commands: std::collections::HashSet<_> = command_spec
+        .get_subcommands()
+        .map(|cmd| cmd.get_name().to_string())
+        .collect();
+
+    expand_alias_chain(&mut args, pos, &known_commands, &aliases)?;
+    Ok(args)
+}
+
+/// Repeatedly expand `args[pos]` while it names an alias rather than a known
+/// command, splicing each expansion in place. Guards against alias cycles
+/// (`foo = "foo"`, or longer loops) via [`MAX_ALIAS_EXPANSIONS`].
+fn expand_alias_chain(
+    args: &mut Vec<std::ffi::OsString>,
+    pos: usize,
+    known_commands: &std::collections::HashSet<String>,
+    aliases: &Aliases,
+) -> Result<()> {
+    let mut expansions = 0;
+    loop {
+        let token = args[pos].to_string_lossy().to_string();
+        if known_commands.contains(&token) {
+            break;
+        }
+        let Some(expansion) = aliases.get(&token) else {
+            // Not an alias either; let clap produce the usual error.
+            break;
+        };
+        expansions += 1;
+        if expansions > MAX_ALIAS_EXPANSIONS {
+            anyhow::bail!("alias `{token}` did not resolve to a command after {MAX_ALIAS_EXPANSIONS} expansions");
+        }
+        let replacement: Vec<_> = expansion.split_whitespace().map(Into::into).collect();
+        args.splice(pos..=pos, replacement);
+    }
+    Ok(())
+}
+
 pub fn execute() -> Result<()> {
-    let command: Vec<_> = std::env::args_os().collect();
+    let start_time = std::time::Instant::now();
+    let start_wall_time = std::time::SystemTime::now();
+    let command: Vec<_> = expand_aliases(std::env::args_os().collect())?;
     let args = Opts::parse_from(&command);
 
     // get global options from command line / env and config file
@@ -175,33 +382,75 @@ pub fn execute() -> Result<()> {
     let mut gopts = args.global;
     config_file.merge_into("global", &mut gopts)?;
 
+    let blackbox_path = gopts.blackbox.clone();
+    let crash_dir = gopts.crash_dir.clone();
+    let profile = args.config_profile.clone();
+    let command: String = command
+        .into_iter()
+        .map(|s| s.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut ctx = blackbox::RunContext::new(command.clone());
+
+    let result = run(args.command, args.repository, gopts, config_file, command, &mut ctx);
+
+    if let Err(err) = &result {
+        let dir = crash_dir.unwrap_or_else(std::env::temp_dir);
+        match crash_report::write(&dir, &ctx.command, &profile, ctx.repository_id.as_deref(), err) {
+            Ok(path) => eprintln!("wrote crash report to {}", path.display()),
+            Err(report_err) => log::warn!("failed to write crash report: {report_err}"),
+        }
+    }
+
+    if let Some(path) = blackbox_path {
+        if let Err(err) =
+            blackbox::append_record(&path, &ctx, start_wall_time, start_time.elapsed(), &result)
+        {
+            log::warn!("failed to write blackbox audit log to {}: {err}", path.display());
+        }
+    }
+
+    result
+}
+
+fn run(
+    command: Command,
+    repository: RepositoryOptions,
+    gopts: GlobalOpts,
+    config_file: RusticConfig,
+    command_line: String,
+    ctx: &mut blackbox::RunContext,
+) -> Result<()> {
     // start logger
     let level_filter = gopts.log_level.unwrap_or(LevelFilter::Info);
-    match &gopts.log_file {
-        None => TermLogger::init(
-            level_filter,
-            ConfigBuilder::new()
-                .set_time_level(LevelFilter::Off)
-                .build(),
-            TerminalMode::Stderr,
-            ColorChoice::Auto,
-        )?,
-
-        Some(file) => CombinedLogger::init(vec![
-            TermLogger::new(
-                level_filter.max(LevelFilter::Warn),
+    match gopts.log_format.unwrap_or_default() {
+        LogFormat::Json => json_log::init(level_filter)?,
+        LogFormat::Text => match &gopts.log_file {
+            None => TermLogger::init(
+                level_filter,
                 ConfigBuilder::new()
                     .set_time_level(LevelFilter::Off)
                     .build(),
                 TerminalMode::Stderr,
                 ColorChoice::Auto,
-            ),
-            WriteLogger::new(
-                level_filter,
-                Config::default(),
-                File::options().create(true).append(true).open(file)?,
-            ),
-        ])?,
+            )?,
+
+            Some(file) => CombinedLogger::init(vec![
+                TermLogger::new(
+                    level_filter.max(LevelFilter::Warn),
+                    ConfigBuilder::new()
+                        .set_time_level(LevelFilter::Off)
+                        .build(),
+                    TerminalMode::Stderr,
+                    ColorChoice::Auto,
+                ),
+                WriteLogger::new(
+                    level_filter,
+                    Config::default(),
+                    File::options().create(true).append(true).open(file)?,
+                ),
+            ])?,
+        },
     }
 
     if gopts.no_progress {
@@ -214,36 +463,32 @@ pub fn execute() -> Result<()> {
         *interval = *duration;
     }
 
-    if let Command::SelfUpdate(opts) = args.command {
+    if let Command::SelfUpdate(opts) = command {
         self_update::execute(opts)?;
         return Ok(());
     }
 
-    if let Command::Completions(opts) = args.command {
+    if let Command::Completions(opts) = command {
         completions::execute(opts);
         return Ok(());
     }
 
-    let command: String = command
-        .into_iter()
-        .map(|s| s.to_string_lossy().to_string())
-        .collect::<Vec<_>>()
-        .join(" ");
-
-    let mut repo_opts = args.repository;
+    let mut repo_opts = repository;
     config_file.merge_into("repository", &mut repo_opts)?;
     let repo = Repository::new(repo_opts)?;
 
-    if let Command::Init(opts) = args.command {
+    if let Command::Init(opts) = command {
         let config_ids = repo.be.list(FileType::Config)?;
         return init::execute(&repo.be, &repo.be_hot, opts, repo.password()?, config_ids);
     }
 
     let repo = repo.open()?;
+    ctx.repository_id = Some(repo.config().id.to_string());
+    requirements::check_repo_requirements(repo.config().version, &command, gopts.ignore_unsupported)?;
 
     #[allow(clippy::match_same_arms)]
-    match args.command {
-        Command::Backup(opts) => backup::execute(repo, gopts, opts, config_file, command)?,
+    match command {
+        Command::Backup(opts) => backup::execute(repo, gopts, opts, config_file, command_line)?,
         Command::Config(opts) => config::execute(repo, opts)?,
         Command::Cat(opts) => cat::execute(repo, opts, config_file)?,
         Command::Check(opts) => check::execute(repo, opts)?,
@@ -256,7 +501,7 @@ pub fn execute() -> Result<()> {
         Command::Key(opts) => key::execute(repo, opts)?,
         Command::List(opts) => list::execute(repo, opts)?,
         Command::Ls(opts) => ls::execute(repo, opts, config_file)?,
-        Command::Merge(opts) => merge_cmd::execute(repo, opts, config_file, command)?,
+        Command::Merge(opts) => merge_cmd::execute(repo, opts, config_file, command_line)?,
         Command::SelfUpdate(_) => {} // already handled above
         Command::Snapshots(opts) => snapshots::execute(repo, opts, config_file)?,
         Command::Prune(opts) => prune::execute(repo, gopts, opts, vec![])?,
@@ -274,3 +519,81 @@ fn verify_cli() {
     use clap::CommandFactory;
     Opts::command().debug_assert()
 }
+
+#[cfg(test)]
+mod alias_tests {
+    use super::*;
+
+    fn flags() -> std::collections::HashSet<String> {
+        use clap::CommandFactory;
+        value_taking_flags(&Opts::command())
+    }
+
+    fn os_args(tokens: &[&str]) -> Vec<std::ffi::OsString> {
+        tokens.iter().map(std::ffi::OsString::from).collect()
+    }
+
+    #[test]
+    fn finds_subcommand_with_no_leading_flags() {
+        let args = os_args(&["rustic", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(1));
+    }
+
+    #[test]
+    fn skips_short_value_taking_flag_and_its_value() {
+        // `-P` takes a value, so `work` must not be mistaken for the subcommand.
+        let args = os_args(&["rustic", "-P", "work", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(3));
+    }
+
+    #[test]
+    fn skips_bundled_short_flags_ending_in_value_taking_flag() {
+        // `-nP` bundles the boolean `-n` with the value-taking `-P`; `work`
+        // is still `-P`'s value, not the subcommand.
+        let args = os_args(&["rustic", "-nP", "work", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(3));
+    }
+
+    #[test]
+    fn skips_bundled_short_flags_with_glued_value() {
+        // `-nPwork` is `-n` bundled with `-P` whose value is glued on.
+        let args = os_args(&["rustic", "-nPwork", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(2));
+    }
+
+    #[test]
+    fn skips_long_value_taking_flag_and_its_value() {
+        let args = os_args(&["rustic", "--config-profile", "work", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(3));
+    }
+
+    #[test]
+    fn skips_inline_value_flag() {
+        let args = os_args(&["rustic", "--config-profile=work", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(2));
+    }
+
+    #[test]
+    fn boolean_flag_does_not_consume_next_token() {
+        let args = os_args(&["rustic", "--no-progress", "sn"]);
+        assert_eq!(first_free_token(&args, &flags()), Some(2));
+    }
+
+    #[test]
+    fn expands_alias_to_full_command() {
+        let aliases: Aliases = serde_json::from_str(r#"{"sn":"snapshots --group-by host"}"#).unwrap();
+        let known: std::collections::HashSet<String> = ["snapshots".to_string()].into_iter().collect();
+        let mut args = os_args(&["rustic", "sn"]);
+        expand_alias_chain(&mut args, 1, &known, &aliases).unwrap();
+        assert_eq!(args, os_args(&["rustic", "snapshots", "--group-by", "host"]));
+    }
+
+    #[test]
+    fn detects_alias_cycle() {
+        let aliases: Aliases = serde_json::from_str(r#"{"a":"b","b":"a"}"#).unwrap();
+        let known = std::collections::HashSet::new();
+        let mut args = os_args(&["rustic", "a"]);
+        let err = expand_alias_chain(&mut args, 1, &known, &aliases).unwrap_err();
+        assert!(err.to_string().contains("did not resolve to a command"));
+    }
+}
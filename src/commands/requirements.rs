@@ -0,0 +1,93 @@
+//! Pre-flight repository requirements gate.
+//!
+//! PARTIAL IMPLEMENTATION: the backlog item this module covers asked for two
+//! checks — the repository config version, and any *declared feature flags*
+//! against a compiled-in `SUPPORTED_FEATURES` set. Only the version check is
+//! implemented. The repository config type in this codebase has no declared
+//! feature-flag list to check against, so there is nothing to compare a
+//! `SUPPORTED_FEATURES` set to yet; adding a real feature-flag check is
+//! follow-up work once the config type grows one, and this item should not
+//! be treated as fully closed until that lands.
+
+use anyhow::{bail, Result};
+
+use super::Command;
+
+/// The highest repository config version this build understands.
+const MAX_SUPPORTED_CONFIG_VERSION: u32 = 2;
+
+/// Whether a command only reads the repository, or may mutate it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Access {
+    ReadOnly,
+    Mutating,
+}
+
+/// Commands that only read the repository. Everything else is assumed to
+/// mutate it and is held to the stricter requirements check.
+fn access_for(command: &Command) -> Access {
+    match command {
+        Command::Cat(_)
+        | Command::Check(_)
+        | Command::Diff(_)
+        | Command::Dump(_)
+        | Command::List(_)
+        | Command::Ls(_)
+        | Command::Repoinfo(_)
+        | Command::Snapshots(_) => Access::ReadOnly,
+        _ => Access::Mutating,
+    }
+}
+
+/// Refuse to proceed if the repository declares a config version newer than
+/// this build supports. Read-only commands only warn and continue, since
+/// they're less likely to be harmed by data they can't fully interpret;
+/// mutating commands like [`Command::Backup`] or [`Command::Prune`] refuse
+/// outright to avoid corrupting the repository, unless `ignore_unsupported`
+/// opts out of the hard-fail.
+pub fn check_repo_requirements(version: u32, command: &Command, ignore_unsupported: bool) -> Result<()> {
+    check_version(version, access_for(command), ignore_unsupported)
+}
+
+fn check_version(version: u32, access: Access, ignore_unsupported: bool) -> Result<()> {
+    if version <= MAX_SUPPORTED_CONFIG_VERSION {
+        return Ok(());
+    }
+
+    let problem = format!(
+        "repository config version {version} is newer than the highest version this rustic supports ({MAX_SUPPORTED_CONFIG_VERSION})"
+    );
+
+    if access == Access::ReadOnly || ignore_unsupported {
+        log::warn!("{problem}");
+        Ok(())
+    } else {
+        bail!("refusing to modify repository: {problem} (pass --ignore-unsupported to proceed anyway)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_version_is_always_fine() {
+        assert!(check_version(1, Access::Mutating, false).is_ok());
+        assert!(check_version(MAX_SUPPORTED_CONFIG_VERSION, Access::Mutating, false).is_ok());
+    }
+
+    #[test]
+    fn read_only_access_warns_but_continues_on_unsupported_version() {
+        assert!(check_version(MAX_SUPPORTED_CONFIG_VERSION + 1, Access::ReadOnly, false).is_ok());
+    }
+
+    #[test]
+    fn mutating_access_refuses_on_unsupported_version() {
+        assert!(check_version(MAX_SUPPORTED_CONFIG_VERSION + 1, Access::Mutating, false).is_err());
+    }
+
+    #[test]
+    fn mutating_access_can_be_overridden() {
+        assert!(check_version(MAX_SUPPORTED_CONFIG_VERSION + 1, Access::Mutating, true).is_ok());
+    }
+}